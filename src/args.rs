@@ -3,20 +3,84 @@ use std::thread;
 
 use anstyle::AnsiColor;
 use anyhow::{Error, anyhow};
-use clap::Parser;
-use clap::ValueHint;
 use clap::builder::{ValueParser, styling::Styles};
+use clap::{Parser, Subcommand, ValueEnum, ValueHint};
 use normpath::PathExt;
 
+/// When to colorize flagged directory paths in human-readable output.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+pub enum ColorWhen {
+    /// Colorize only when stdout is a terminal
+    #[default]
+    Auto,
+    /// Always colorize
+    Always,
+    /// Never colorize
+    Never,
+}
+
+/// Output mode for offender, calibration and summary reporting.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+pub enum OutputFormat {
+    /// Colored, human-readable text (default)
+    #[default]
+    Text,
+    /// Pretty-printed JSON, one record per emitted event
+    Json,
+    /// Newline-delimited JSON, one record per emitted event
+    Ndjson,
+}
+
+/// Byte unit format used when printing directory sizes, modeled on dua-cli's `ByteFormat`.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[clap(rename_all = "lower")]
+pub enum ByteFormat {
+    /// Human-readable metric units (kB, MB, GB, ...), base 1000
+    #[default]
+    Metric,
+    /// Human-readable binary units (KiB, MiB, GiB, ...), base 1024
+    Binary,
+    /// Raw byte count with no unit conversion
+    Bytes,
+    /// Fixed megabyte (base 1000) units
+    Mb,
+    /// Fixed mebibyte (base 1024) units
+    Mib,
+    /// Fixed gigabyte (base 1000) units
+    Gb,
+    /// Fixed gibibyte (base 1024) units
+    Gib,
+}
+
 const STYLES: Styles = Styles::styled()
     .header(AnsiColor::Yellow.on_default())
     .usage(AnsiColor::Green.on_default())
     .literal(AnsiColor::Green.on_default())
     .placeholder(AnsiColor::Green.on_default());
 
-#[derive(Parser, Default, Debug, Clone)]
+/// Top-level command line, split into a `scan` subcommand that walks and reports on
+/// directories and a `calibrate` subcommand that only determines the size-to-inode ratio for
+/// a filesystem.
+#[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None, styles=STYLES)]
-pub struct Args {
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scan one or more paths for directories with an abnormally high file count
+    Scan(ScanArgs),
+    /// Determine the size-to-inode ratio for a filesystem without scanning, so it can be
+    /// reused across many `scan` runs via `--size-inode-ratio`
+    Calibrate(CalibrateArgs),
+}
+
+#[derive(clap::Args, Default, Debug, Clone)]
+pub struct ScanArgs {
     /// Follow symlinks
     #[clap(short = 'f', long, action = clap::ArgAction::Set, default_value_t = false)]
     pub follow_symlinks: bool,
@@ -58,16 +122,95 @@ pub struct Args {
     #[clap(short = 't', long, value_parser, value_hint = ValueHint::AnyPath)]
     pub calibration_path: Option<PathBuf>,
 
+    /// Use a built-in size-to-inode ratio for filesystems known to support it, skipping the
+    /// destructive calibration step entirely
+    #[clap(short = 'b', long, action = clap::ArgAction::Set, default_value_t = false)]
+    pub use_builtin_ratio: bool,
+
     /// Directories to exclude from scanning
     #[clap(short = 's', long, value_parser, value_hint = ValueHint::AnyPath)]
     pub skip_path: Vec<PathBuf>,
 
+    /// Glob patterns to exclude from scanning, matched against each directory's path (e.g.
+    /// `--skip-glob '**/node_modules'`)
+    #[clap(short = 'g', long)]
+    pub skip_glob: Vec<String>,
+
+    /// Honor .gitignore/.ignore files and global git excludes while walking
+    #[clap(long, action = clap::ArgAction::Set, default_value_t = false)]
+    pub respect_ignore: bool,
+
+    /// Stat each directory on Unix by opening its parent and calling `fstatat` on the entry's
+    /// name instead of a single path-based `stat`; the parent is opened by path on every call
+    /// and no directory descriptor is kept open between entries, at the cost of more syscalls
+    /// than a plain `stat`, a no-op on other platforms
+    #[clap(long, action = clap::ArgAction::Set, default_value_t = false)]
+    pub use_openat: bool,
+
+    /// Buffer every offender and print only the top N (sorted by file count) once the scan
+    /// completes, instead of streaming alerts as they are found
+    #[clap(long, value_parser)]
+    pub top: Option<u64>,
+
+    /// Buffer every offender and print them sorted by estimated file count (largest first)
+    /// once the scan completes, instead of streaming alerts as they are found; falls back to
+    /// streaming partway through if `--max-buffer-time` elapses first, modeled on `fd`'s
+    /// buffering-vs-streaming output modes
+    #[clap(long, action = clap::ArgAction::Set, default_value_t = false)]
+    pub sort_by_size: bool,
+
+    /// Milliseconds to buffer offenders for `--sort-by-size` before flipping to streaming
+    /// output so memory stays bounded on very large trees; has no effect unless
+    /// `--sort-by-size` is set
+    #[clap(long, value_parser)]
+    pub max_buffer_time: Option<u64>,
+
+    /// Byte unit format used when printing directory sizes
+    #[clap(long, value_enum, default_value_t = ByteFormat::Metric)]
+    pub bytes_format: ByteFormat,
+
+    /// Print raw file counts instead of SI-abbreviated human counts (e.g. 1.2M)
+    #[clap(long, action = clap::ArgAction::Set, default_value_t = false)]
+    pub no_si: bool,
+
+    /// Output mode: human-readable text, or machine-readable json/ndjson records
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// When to colorize flagged directory paths, using the `LS_COLORS` environment variable
+    #[clap(long, value_enum, default_value_t = ColorWhen::Auto)]
+    pub color: ColorWhen,
+
     /// Paths to check for large directories
     #[clap(required = true, value_parser = ValueParser::new(parse_paths), value_hint = ValueHint::AnyPath
     )]
     pub path: Vec<PathBuf>,
 }
 
+/// Arguments for the `calibrate` subcommand, which only runs the destructive calibration step
+/// against each given path and prints the resulting size-to-inode ratio.
+#[derive(clap::Args, Default, Debug, Clone)]
+pub struct CalibrateArgs {
+    /// Calibration directory file count
+    #[clap(short = 'c', long, value_parser, default_value_t = crate::calibrate::DEFAULT_TEST_COUNT)]
+    pub calibration_count: u64,
+
+    /// Number of threads to use when calibrating
+    #[clap(short = 'x', long, value_parser = ValueParser::new(parse_threads), default_value_t = thread::available_parallelism().map(| n | n.get()).unwrap_or(2)
+    )]
+    pub threads: usize,
+
+    /// Output mode: human-readable text, or machine-readable json/ndjson records
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Filesystem paths to calibrate; a temporary calibration directory is created and removed
+    /// inside each one
+    #[clap(required = true, value_parser = ValueParser::new(parse_paths), value_hint = ValueHint::AnyPath
+    )]
+    pub path: Vec<PathBuf>,
+}
+
 /// Parse and validate threads option
 fn parse_threads(x: &str) -> Result<usize, Error> {
     match x.parse::<usize>() {
@@ -1,5 +1,4 @@
 use std::fs::File;
-use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::process;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -10,7 +9,8 @@ use fs_err as fs;
 use rayon::prelude::*;
 use rm_rf::ensure_removed;
 
-use crate::{args, progress};
+use crate::args::OutputFormat;
+use crate::{os, progress, report};
 
 /// Default number of files to create in the calibration directory
 pub const DEFAULT_TEST_COUNT: u64 = 100_000;
@@ -25,11 +25,15 @@ const ERROR_EXIT: i32 = 1;
 /// It uses a multi-threaded approach to create files and monitors for a shutdown signal
 /// to safely terminate and clean up if necessary.
 ///
+/// Shared by the `scan` and `calibrate` subcommands, so it takes the few settings it actually
+/// needs rather than either subcommand's full argument struct.
+///
 /// # Arguments
 /// * `test_path` - A reference to the path where test files will be created.
 /// * `shutdown` - A shared atomic boolean to signal shutdown and cleanup.
-/// * `args` - A shared structure containing runtime arguments such as the number of threads
-///   and the number of files to create for calibration.
+/// * `threads` - The number of threads to use for mass file creation.
+/// * `calibration_count` - The number of files to create for calibration.
+/// * `output` - The output mode to report calibration progress and results in.
 ///
 /// # Returns
 /// Returns a `Result<u64, Error>` which is the calculated size-to-inode ratio if successful,
@@ -43,11 +47,7 @@ const ERROR_EXIT: i32 = 1;
 /// ```
 /// let test_path = Path::new("/tmp/test_dir");
 /// let shutdown = Arc::new(AtomicBool::new(false));
-/// let args = Arc::new(args::Args {
-///     threads: 4,
-///     calibration_count: 1000,
-/// });
-/// let ratio = get_inode_ratio(&test_path, &shutdown, &args);
+/// let ratio = get_inode_ratio(&test_path, &shutdown, 4, 1000, OutputFormat::Text);
 /// match ratio {
 ///     Ok(ratio) => println!("Size-to-inode ratio: {}", ratio),
 ///     Err(e) => println!("Failed to calculate size-to-inode ratio: {}", e),
@@ -56,42 +56,55 @@ const ERROR_EXIT: i32 = 1;
 pub fn get_inode_ratio(
     test_path: &Path,
     shutdown: &Arc<AtomicBool>,
-    args: &Arc<args::Args>,
+    threads: usize,
+    calibration_count: u64,
+    output: OutputFormat,
 ) -> Result<u64, Error> {
-    println!(
-        "Starting test directory calibration in {}",
-        test_path.display(),
+    report::status(
+        output,
+        &format!("Starting test directory calibration in {}", test_path.display()),
     );
 
     // Thread pool for mass file creation
     let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(args.threads)
+        .num_threads(threads)
         .build()
         .context("Unable to spawn calibration thread pool")?;
 
-    let pb = progress::new_spinner("Creating test files in progress...");
+    // The spinner is decorative and would interleave with machine-readable output
+    let pb = (output == OutputFormat::Text)
+        .then(|| progress::new_spinner("Creating test files in progress..."));
 
     // Mass create files; filenames are short to get minimal size to inode ratio
     pool.install(|| {
-        (0..args.calibration_count).into_par_iter().for_each(|i| {
+        (0..calibration_count).into_par_iter().for_each(|i| {
             if !shutdown.load(Ordering::Acquire) {
                 File::create(test_path.join(i.to_string())).expect("Unable to create files");
             }
         });
     });
 
-    pb.finish_with_message("Done.");
+    if let Some(pb) = pb {
+        pb.finish_with_message("Done.");
+    }
 
     // Terminate on received interrupt signal
     if shutdown.load(Ordering::Acquire) {
-        println!("Requested program exit, stopping and deleting temporary files...",);
+        report::status(
+            output,
+            "Requested program exit, stopping and deleting temporary files...",
+        );
         ensure_removed(test_path)
             .expect("Unable to completely delete calibration directory, exiting");
         process::exit(ERROR_EXIT);
     }
 
-    let size_inode_ratio = fs::metadata(test_path)?.size() / args.calibration_count;
-    println!("Calibration done. Calculated size-to-inode ratio: {size_inode_ratio}");
+    // Clamped to at least 1: on filesystems where directory size doesn't grow with entry count
+    // (xfs, btrfs, tmpfs, overlayfs) this division can otherwise yield 0, which would later
+    // divide-by-zero when approximating a scanned directory's file count from its size.
+    let size_inode_ratio =
+        (os::dir_entry_size(&fs::metadata(test_path)?) / calibration_count).max(1);
+    report::calibration(output, test_path, size_inode_ratio);
 
     Ok(size_inode_ratio)
 }
@@ -0,0 +1,139 @@
+use std::path::Path;
+
+/// `statfs(2)` magic numbers for filesystems relevant to the entry-count heuristic.
+#[cfg(target_os = "linux")]
+mod magic {
+    /// Shared by ext2, ext3 and ext4, which are on-disk compatible.
+    pub const EXT_SUPER_MAGIC: i64 = 0xEF53;
+    pub const XFS_SUPER_MAGIC: i64 = 0x5846_5342;
+    pub const BTRFS_SUPER_MAGIC: i64 = 0x9123_683e;
+    pub const TMPFS_MAGIC: i64 = 0x0102_1994;
+    pub const OVERLAYFS_SUPER_MAGIC: i64 = 0x794c_7630;
+}
+
+/// Whether directory size is known to grow with entry count on a given filesystem, which is
+/// the assumption the `size_inode_ratio` heuristic depends on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Correlation {
+    /// Directory size is known to correlate with entry count (e.g. ext2/3/4).
+    Correlates,
+    /// Directory size is known NOT to correlate with entry count (e.g. xfs, btrfs, tmpfs,
+    /// overlayfs); approximate mode is meaningless there.
+    DoesNotCorrelate,
+    /// The filesystem magic is not recognised; assume today's default behaviour.
+    Unknown,
+}
+
+/// Classifies the filesystem backing `path` by calling `statfs(2)` and matching its magic
+/// number against filesystems known to correlate (or not) with the entry-count heuristic.
+///
+/// Returns `Correlation::Unknown` if the `statfs` call fails.
+#[cfg(target_os = "linux")]
+pub fn classify(path: &Path) -> Correlation {
+    statfs_magic(path).map_or(Correlation::Unknown, correlation_for_magic)
+}
+
+/// Built-in directory-entry-to-inode-size ratio for filesystems where this is known to be
+/// stable, letting callers skip the (destructive, ~100k-file) calibration step entirely.
+#[cfg(target_os = "linux")]
+pub fn known_ratio(path: &Path) -> Option<u64> {
+    ratio_for_magic(statfs_magic(path)?)
+}
+
+/// Matches a `statfs(2)` magic number against filesystems known to correlate (or not) with the
+/// entry-count heuristic. Split out from [`classify`] so the mapping can be unit-tested without
+/// a real `statfs(2)` call.
+#[cfg(target_os = "linux")]
+fn correlation_for_magic(f_type: i64) -> Correlation {
+    match f_type {
+        magic::EXT_SUPER_MAGIC => Correlation::Correlates,
+        magic::XFS_SUPER_MAGIC
+        | magic::BTRFS_SUPER_MAGIC
+        | magic::TMPFS_MAGIC
+        | magic::OVERLAYFS_SUPER_MAGIC => Correlation::DoesNotCorrelate,
+        _ => Correlation::Unknown,
+    }
+}
+
+/// Matches a `statfs(2)` magic number against the built-in ratio table. Split out from
+/// [`known_ratio`] so the mapping can be unit-tested without a real `statfs(2)` call.
+#[cfg(target_os = "linux")]
+fn ratio_for_magic(f_type: i64) -> Option<u64> {
+    match f_type {
+        magic::EXT_SUPER_MAGIC => Some(24),
+        _ => None,
+    }
+}
+
+/// Calls `statfs(2)` on `path` and returns its `f_type` magic number, or `None` if the call
+/// fails (missing permissions, dangling path, etc.).
+#[cfg(target_os = "linux")]
+fn statfs_magic(path: &Path) -> Option<i64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    Some(i64::from(stat.f_type))
+}
+
+/// Filesystem type detection is not implemented on non-Linux targets; always reports
+/// `Correlation::Unknown` so today's default behaviour is preserved there.
+#[cfg(not(target_os = "linux"))]
+pub fn classify(_path: &Path) -> Correlation {
+    Correlation::Unknown
+}
+
+/// No built-in ratio table is available on non-Linux targets.
+#[cfg(not(target_os = "linux"))]
+pub fn known_ratio(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn correlation_for_magic_matches_known_filesystems() {
+        let cases = [
+            (magic::EXT_SUPER_MAGIC, Correlation::Correlates),
+            (magic::XFS_SUPER_MAGIC, Correlation::DoesNotCorrelate),
+            (magic::BTRFS_SUPER_MAGIC, Correlation::DoesNotCorrelate),
+            (magic::TMPFS_MAGIC, Correlation::DoesNotCorrelate),
+            (magic::OVERLAYFS_SUPER_MAGIC, Correlation::DoesNotCorrelate),
+            (0x1234_5678, Correlation::Unknown),
+        ];
+
+        for (f_type, expected) in cases {
+            assert_eq!(correlation_for_magic(f_type), expected, "f_type {f_type:#x}");
+        }
+    }
+
+    #[test]
+    fn overlayfs_magic_matches_linux_kernel_value() {
+        // Regression test for a transposed-digit typo that silently made `classify()` never
+        // recognise overlayfs mounts.
+        assert_eq!(magic::OVERLAYFS_SUPER_MAGIC, 0x794c_7630);
+    }
+
+    #[test]
+    fn ratio_for_magic_only_known_for_ext() {
+        let cases = [
+            (magic::EXT_SUPER_MAGIC, Some(24)),
+            (magic::XFS_SUPER_MAGIC, None),
+            (magic::BTRFS_SUPER_MAGIC, None),
+            (magic::TMPFS_MAGIC, None),
+            (magic::OVERLAYFS_SUPER_MAGIC, None),
+        ];
+
+        for (f_type, expected) in cases {
+            assert_eq!(ratio_for_magic(f_type), expected, "f_type {f_type:#x}");
+        }
+    }
+}
@@ -1,6 +1,5 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use std::os::unix::fs::MetadataExt;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::time::Instant;
@@ -10,15 +9,21 @@ use anyhow::{Context, Error, Result};
 use clap::Parser;
 use fdlimit::{Outcome, raise_fd_limit};
 use fs_err as fs;
-use indicatif::HumanDuration;
 use tempfile::TempDir;
 
 mod args;
 mod calibrate;
+mod count;
+mod format;
+mod fstype;
 mod interrupt;
+mod os;
 mod progress;
+mod report;
 mod walk;
 
+use args::{CalibrateArgs, Command, OutputFormat, ScanArgs};
+
 use mimalloc::MiMalloc;
 
 #[global_allocator]
@@ -26,31 +31,41 @@ static GLOBAL: MiMalloc = MiMalloc;
 
 /// Entry point for the filesystem scanning application.
 ///
-/// This function sets up necessary configurations and initiates the parallel filesystem scan
-/// by calling `parallel_search`. It handles command-line arguments and sets up the environment
-/// for the application to run.
+/// This function sets up necessary configurations and dispatches to the `scan` or
+/// `calibrate` subcommand. It handles command-line arguments and sets up the environment for
+/// the application to run.
 ///
 /// # Behavior:
-/// - Parses command-line arguments to configure the scanning process.
+/// - Parses command-line arguments to pick and configure a subcommand.
 /// - Sets up signal handling for graceful shutdowns.
-/// - Initiates the filesystem scan by calling `parallel_search` with appropriate parameters.
-/// - Handles any errors returned by `parallel_search` and exits with an appropriate status code.
+/// - Runs the selected subcommand and propagates any error it returns.
 ///
 /// # Returns:
 /// - Typically does not return and calls `std::process::exit` to terminate the program.
 fn main() -> Result<(), Error> {
-    let args = Arc::new(args::Args::parse());
+    let cli = args::Cli::parse();
 
     // Setup termination signal (SIGINT, SIGTERM and SIGQUIT) handlers that will cause program to stop
     let shutdown = Arc::new(AtomicBool::new(false));
-    let shutdown_walk = shutdown.clone();
     interrupt::setup_interrupt_handler(&shutdown)?;
 
-    println!("Using {} threads for calibration and scanning", args.threads);
+    match cli.command {
+        Command::Scan(args) => run_scan(Arc::new(args), &shutdown),
+        Command::Calibrate(args) => run_calibrate(&args, &shutdown),
+    }
+}
+
+/// Runs the `scan` subcommand: walks every given path in turn, calibrating the size-to-inode
+/// ratio first unless one was already supplied or looked up.
+fn run_scan(args: Arc<ScanArgs>, shutdown: &Arc<AtomicBool>) -> Result<(), Error> {
+    report::status(
+        args.output,
+        &format!("Using {} threads for calibration and scanning", args.threads),
+    );
 
     // Attempt to raise FD limit
     if let Ok(Outcome::LimitRaised { to: x, .. }) = raise_fd_limit() {
-        println!("Maximum number of file descriptors available: {x}");
+        report::status(args.output, &format!("Maximum number of file descriptors available: {x}"));
     }
 
     // Search only unique paths
@@ -63,28 +78,67 @@ fn main() -> Result<(), Error> {
             _ => continue,
         };
 
-        println!("Started analysis for path {}", path.display());
+        report::status(args.output, &format!("Started analysis for path {}", path.display()));
 
         // Retrieve Unix metadata for top search path
         let path_metadata = fs::metadata(&path)
             .context("Unable to retrieve top search directory metadata")?;
 
+        // The inode-size/entry-count heuristic only holds on filesystems where directory size
+        // actually grows with entry count (ext2/3/4); on filesystems known not to correlate
+        // (xfs, btrfs, tmpfs, overlayfs) it would never trip the alert/blacklist thresholds at
+        // all, so force `--accurate` there, which makes offender detection itself (not just the
+        // reported count) fall back to an exact `count::count_entries` per directory.
+        let mut path_args = (*args).clone();
+
+        if fstype::classify(&path) == fstype::Correlation::DoesNotCorrelate {
+            report::status(
+                args.output,
+                &format!(
+                    "Warning: {} is on a filesystem where directory size is not known to correlate with entry count; switching to --accurate so offender detection uses exact directory entry counts there",
+                    path.display()
+                ),
+            );
+            path_args.accurate = true;
+        }
+
+        let path_args = Arc::new(path_args);
+
         // Directory inode size to number of entries ratio is either manually provided in
-        // `args.size_inode_ratio` or determined from manually provided calibration path
+        // `args.size_inode_ratio`, looked up from the built-in ratio table when
+        // `args.use_builtin_ratio` is set, determined from manually provided calibration path
         // `args.calibration_path` or determined from calibration directory created in search root
         // `TempDir::new_in(path.as_path())`
-        let size_inode_ratio = if args.size_inode_ratio > 0 {
-            args.size_inode_ratio
-        } else if let Some(ref user_path) = args.calibration_path {
+        let size_inode_ratio = if path_args.size_inode_ratio > 0 {
+            path_args.size_inode_ratio
+        } else if let Some(ratio) = path_args
+            .use_builtin_ratio
+            .then(|| fstype::known_ratio(&path))
+            .flatten()
+        {
+            report::status(
+                args.output,
+                &format!(
+                    "Using built-in size-to-inode ratio {ratio} for the detected filesystem at {}, skipping calibration",
+                    path.display()
+                ),
+            );
+
+            ratio
+        } else if let Some(ref user_path) = path_args.calibration_path {
             // User has specified his calibration directory so attempt to check if it resides on
             // the same device
-            if fs::metadata(user_path.as_path()).context(
+            let user_path_metadata = fs::metadata(user_path.as_path()).context(
                 "Unable to retrieve user-specified calibration directory metadata",
-            )?.dev() != path_metadata.dev()
-            {
-                println!(
-                    "Oops, test directory resides on a different device than path {}, results are possibly unreliable!",
-                    path.display()
+            )?;
+
+            if !os::same_volume(&user_path_metadata, &path_metadata) {
+                report::status(
+                    args.output,
+                    &format!(
+                        "Oops, test directory resides on a different device than path {}, results are possibly unreliable!",
+                        path.display()
+                    ),
                 );
             }
 
@@ -94,40 +148,81 @@ fn main() -> Result<(), Error> {
                     "Unable to setup/create calibration test directory",
                 )?);
 
-            calibrate::get_inode_ratio(tmp_dir.path(), &shutdown_walk, &args)
-                .context("Unable to calibrate inode to size ratio")?
+            calibrate::get_inode_ratio(
+                tmp_dir.path(),
+                shutdown,
+                path_args.threads,
+                path_args.calibration_count,
+                path_args.output,
+            )
+            .context("Unable to calibrate inode to size ratio")?
         } else {
             // Prepare temporary calibration directory in root of the search path
             let tmp_dir = Arc::new(TempDir::new_in(path.as_path()).context(
                 "Unable to setup/create calibration test directory",
             )?);
 
-            calibrate::get_inode_ratio(tmp_dir.path(), &shutdown_walk, &args)
-                .context("Unable to calibrate inode to size ratio")?
+            calibrate::get_inode_ratio(
+                tmp_dir.path(),
+                shutdown,
+                path_args.threads,
+                path_args.calibration_count,
+                path_args.output,
+            )
+            .context("Unable to calibrate inode to size ratio")?
         };
 
         let start = Instant::now();
-        let pb = progress::new_spinner(format!(
-            "Scanning path {} in progress...",
-            path.display()
-        ));
+
+        // The spinner is decorative and would interleave with machine-readable output
+        let pb = (path_args.output == OutputFormat::Text).then(|| {
+            progress::new_spinner(format!("Scanning path {} in progress...", path.display()))
+        });
 
         let dir_count = walk::parallel_search(
             &path,
             &path_metadata,
             size_inode_ratio,
-            &shutdown_walk,
-            &args,
+            shutdown,
+            &path_args,
         );
 
-        pb.finish_with_message("Done.");
+        if let Some(pb) = pb {
+            pb.finish_with_message("Done.");
+        }
+
+        report::scan_summary(path_args.output, &path, dir_count, start.elapsed());
+    }
+
+    Ok(())
+}
+
+/// Runs the `calibrate` subcommand: determines and prints the size-to-inode ratio for every
+/// given path, without scanning for offenders.
+fn run_calibrate(args: &CalibrateArgs, shutdown: &Arc<AtomicBool>) -> Result<(), Error> {
+    report::status(args.output, &format!("Using {} threads for calibration", args.threads));
+
+    let mut visited_paths = AHashSet::with_capacity(args.path.len());
+
+    for path in &args.path {
+        match visited_paths.get(path) {
+            None => visited_paths.insert(path.clone()),
+            _ => continue,
+        };
 
-        println!(
-            "Scanning path {} completed. Directories scanned: {}, Time elapsed: {}",
-            path.display(),
-            dir_count,
-            HumanDuration(start.elapsed())
+        // Prepare temporary calibration directory in the target path
+        let tmp_dir = Arc::new(
+            TempDir::new_in(path).context("Unable to setup/create calibration test directory")?,
         );
+
+        calibrate::get_inode_ratio(
+            tmp_dir.path(),
+            shutdown,
+            args.threads,
+            args.calibration_count,
+            args.output,
+        )
+        .context("Unable to calibrate inode to size ratio")?;
     }
 
     Ok(())
@@ -0,0 +1,17 @@
+//! Per-OS metadata abstraction.
+//!
+//! The directory-size/inode-ratio heuristic is inherently platform-specific: on Unix
+//! filesystems where directory size grows with entry count (ext2/3/4) the raw inode size is a
+//! useful signal, while Windows has no equivalent guarantee. Both platforms expose the same
+//! `dir_entry_size`/`same_volume` interface so `calibrate`, `walk` and `main` stay
+//! platform-agnostic.
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+pub use unix::{device_id, dir_entry_size, openat_dir_stat, same_volume};
+#[cfg(windows)]
+pub use windows::{device_id, dir_entry_size, openat_dir_stat, same_volume};
@@ -0,0 +1,40 @@
+use std::ffi::OsStr;
+use std::fs::Metadata;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// Returns the raw inode size of a directory, the basis of the entry-count heuristic on
+/// filesystems where directory size grows with the number of entries (ext2/3/4).
+pub fn dir_entry_size(metadata: &Metadata) -> u64 {
+    metadata.size()
+}
+
+/// Returns `true` when both pieces of metadata reside on the same device.
+pub fn same_volume(a: &Metadata, b: &Metadata) -> bool {
+    a.dev() == b.dev()
+}
+
+/// Returns the device ID backing a directory, used for the machine-readable reports' `device_id`
+/// field.
+pub fn device_id(metadata: &Metadata) -> Option<u64> {
+    Some(metadata.dev())
+}
+
+/// Stats `file_name` relative to its parent directory `parent` via `fstatat` (through the
+/// `openat` crate) rather than resolving `parent/file_name` as a full path from the filesystem
+/// root. Returns the same `(size, device_id)` pair that `dir_entry_size`/`device_id` derive
+/// from a `std::fs::Metadata`.
+///
+/// Backs the opt-in `--use-openat` scan mode. `parent` is re-opened by path on every call rather
+/// than kept open and reused across siblings, so it issues an `open` plus an `fstatat` per child
+/// instead of a single `stat`; the ancestors in `parent`'s path are resolved afresh on each of
+/// those opens, just as they would be by a full-path `stat`.
+pub fn openat_dir_stat(parent: &Path, file_name: &OsStr) -> io::Result<(u64, Option<u64>)> {
+    let stat = openat::Dir::open(parent)?.metadata(file_name)?.stat();
+
+    #[allow(clippy::cast_sign_loss)]
+    let size = stat.st_size as u64;
+
+    Ok((size, Some(stat.st_dev)))
+}
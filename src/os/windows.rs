@@ -0,0 +1,38 @@
+use std::ffi::OsStr;
+use std::fs::Metadata;
+use std::io;
+use std::os::windows::fs::MetadataExt;
+use std::path::Path;
+
+/// Returns the directory's on-disk size.
+///
+/// Unlike ext2/3/4, NTFS directories are B-trees whose allocated size does not grow linearly
+/// with entry count, so the approximate entry-count heuristic derived from this value is not
+/// reliable on Windows. Prefer `--accurate` scanning there; see `crate::fstype` for the
+/// same reasoning applied to Unix filesystems that don't correlate either.
+pub fn dir_entry_size(metadata: &Metadata) -> u64 {
+    metadata.file_size()
+}
+
+/// Always reports directories as residing on the same volume.
+///
+/// The standard library does not expose a volume identifier on `Metadata` for Windows, so
+/// `one_filesystem` enforcement is currently a no-op there; this is a documented limitation
+/// rather than a best-effort guess.
+pub fn same_volume(_a: &Metadata, _b: &Metadata) -> bool {
+    true
+}
+
+/// No volume identifier is available on `Metadata` for Windows.
+pub fn device_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// `--use-openat` has no effect on Windows: there is no `fstatat` equivalent exposed through
+/// `std`, so this always fails and callers fall back to ordinary path-based `stat`.
+pub fn openat_dir_stat(_parent: &Path, _file_name: &OsStr) -> io::Result<(u64, Option<u64>)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "openat-based stat is not supported on this platform",
+    ))
+}
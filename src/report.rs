@@ -0,0 +1,238 @@
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use ansi_term::Colour::{Red, Yellow};
+use indicatif::HumanDuration;
+use lscolors::LsColors;
+use serde::Serialize;
+
+use crate::args::{ColorWhen, OutputFormat, ScanArgs};
+use crate::format;
+
+/// Lazily parsed `LS_COLORS` environment variable, shared across every offender printed in
+/// a run.
+static LS_COLORS: LazyLock<LsColors> = LazyLock::new(|| LsColors::from_env().unwrap_or_default());
+
+/// Whether a flagged directory crossed the alert or blacklist threshold, serialized as a plain
+/// string so NDJSON consumers (`jq`, log collectors) can filter on it without decoding a bool.
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Classification {
+    Alert,
+    Blacklist,
+}
+
+/// A single flagged directory, serialized as one JSON/NDJSON record in machine-readable modes.
+#[derive(Serialize)]
+struct OffenderRecord<'a> {
+    path: &'a Path,
+    inode_size_bytes: u64,
+    approx_files: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact_files: Option<u64>,
+    /// Kept alongside `classification` for consumers already scripted against the original
+    /// boolean field; `true` iff `classification` is `Classification::Blacklist`.
+    blacklisted: bool,
+    classification: Classification,
+    /// Whether the blacklist threshold stopped the walk from descending into this directory.
+    stopped_descending: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_id: Option<u64>,
+}
+
+/// A calibration-summary record, emitted once the size-to-inode ratio is known.
+#[derive(Serialize)]
+struct CalibrationRecord<'a> {
+    path: &'a Path,
+    size_inode_ratio: u64,
+}
+
+/// A scan-summary record, emitted once a top-level search path finishes.
+#[derive(Serialize)]
+struct ScanSummaryRecord<'a> {
+    path: &'a Path,
+    directories_scanned: u64,
+    elapsed_seconds: f64,
+}
+
+/// Notes that a bounded offender buffer (`--top`) overflowed its capacity and dropped some
+/// offenders rather than silently truncating with no signal to the consumer.
+#[derive(Serialize)]
+struct DroppedOffendersRecord {
+    dropped_offenders: u64,
+}
+
+/// Reports a single flagged directory through the human or machine-readable printer selected
+/// by `--output`.
+///
+/// # Arguments
+/// * `args` - Command-line arguments, providing the `bytes_format`, `no_si` and `output`
+///   settings.
+/// * `full_path` - The path of the flagged directory.
+/// * `size` - The directory's inode size in bytes.
+/// * `approx_files` - The estimated file count derived from `size`.
+/// * `exact_files` - The exact entry count, already computed by the caller when `--accurate`
+///   (or a non-correlating filesystem) required one for offender detection itself; `None` when
+///   the heuristic estimate alone drove detection.
+/// * `blacklisted` - Whether the directory exceeded the blacklist threshold.
+/// * `device_id` - The device the directory resides on, when available on this platform.
+pub fn offender(
+    args: &ScanArgs,
+    full_path: &Path,
+    size: u64,
+    approx_files: u64,
+    exact_files: Option<u64>,
+    blacklisted: bool,
+    device_id: Option<u64>,
+) {
+    match args.output {
+        OutputFormat::Text => {
+            print_offender_text(full_path, size, approx_files, exact_files, args, blacklisted);
+        }
+        OutputFormat::Json | OutputFormat::Ndjson => print_record(
+            &OffenderRecord {
+                path: full_path,
+                inode_size_bytes: size,
+                approx_files,
+                exact_files,
+                blacklisted,
+                classification: if blacklisted {
+                    Classification::Blacklist
+                } else {
+                    Classification::Alert
+                },
+                stopped_descending: blacklisted,
+                device_id,
+            },
+            args.output,
+        ),
+    }
+}
+
+fn print_offender_text(
+    full_path: &Path,
+    size: u64,
+    approx_files: u64,
+    exact_files: Option<u64>,
+    args: &ScanArgs,
+    red_alert: bool,
+) {
+    let human_files = format::format_count(exact_files.unwrap_or(approx_files), args.no_si);
+
+    println!(
+        "Found directory {} with inode size {} and {}{} files",
+        colorize_path(args, full_path),
+        format::format_bytes(size, args.bytes_format),
+        if exact_files.is_some() { "" } else { "approx " },
+        if red_alert {
+            Red.paint(human_files)
+        } else {
+            Yellow.paint(human_files)
+        }
+    );
+}
+
+/// Whether paths should be colorized for the current run.
+fn should_colorize(args: &ScanArgs) -> bool {
+    match args.color {
+        ColorWhen::Always => true,
+        ColorWhen::Never => false,
+        ColorWhen::Auto => std::io::stdout().is_terminal(),
+    }
+}
+
+/// Styles a flagged directory path using the `LS_COLORS`-derived style for that path, so a
+/// human scanning a long report can instantly spot entries by type, falling back to plain
+/// text when colorizing is disabled or `LS_COLORS` has no matching entry.
+fn colorize_path(args: &ScanArgs, path: &Path) -> String {
+    if !should_colorize(args) {
+        return path.display().to_string();
+    }
+
+    match LS_COLORS.style_for_path(path) {
+        Some(style) => style
+            .to_ansi_term_style()
+            .paint(path.display().to_string())
+            .to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+/// Reports the calibrated size-to-inode ratio for `path` through the human or machine-readable
+/// printer selected by `output`. Shared by the `scan` and `calibrate` subcommands, so it takes
+/// a bare `OutputFormat` rather than either subcommand's full argument struct.
+pub fn calibration(output: OutputFormat, path: &Path, size_inode_ratio: u64) {
+    match output {
+        OutputFormat::Text => {
+            println!("Calibration done. Calculated size-to-inode ratio: {size_inode_ratio}");
+        }
+        OutputFormat::Json | OutputFormat::Ndjson => print_record(
+            &CalibrationRecord {
+                path,
+                size_inode_ratio,
+            },
+            output,
+        ),
+    }
+}
+
+/// Reports the outcome of a completed top-level scan through the human or machine-readable
+/// printer selected by `output`.
+pub fn scan_summary(output: OutputFormat, path: &Path, dir_count: u64, elapsed: Duration) {
+    match output {
+        OutputFormat::Text => println!(
+            "Scanning path {} completed. Directories scanned: {}, Time elapsed: {}",
+            path.display(),
+            dir_count,
+            HumanDuration(elapsed)
+        ),
+        OutputFormat::Json | OutputFormat::Ndjson => print_record(
+            &ScanSummaryRecord {
+                path,
+                directories_scanned: dir_count,
+                elapsed_seconds: elapsed.as_secs_f64(),
+            },
+            output,
+        ),
+    }
+}
+
+/// Reports that the bounded `--top` offender buffer overflowed and dropped `dropped` offenders,
+/// through the human or machine-readable printer selected by `output`. Unlike [`status`], this
+/// is never suppressed in `json`/`ndjson` modes: a machine-readable consumer needs to know a
+/// `--top` run silently truncated just as much as a human reading text output does.
+pub fn dropped_offenders(output: OutputFormat, dropped: u64) {
+    match output {
+        OutputFormat::Text => println!(
+            "Note: {dropped} offender(s) exceeded the buffered top-N capacity and were dropped; rerun with the default streaming mode for a complete view"
+        ),
+        OutputFormat::Json | OutputFormat::Ndjson => {
+            print_record(&DroppedOffendersRecord { dropped_offenders: dropped }, output);
+        }
+    }
+}
+
+/// Prints a plain status line, suppressed in `json`/`ndjson` modes so that every line of
+/// stdout stays a well-formed structured record. Shared by the `scan` and `calibrate`
+/// subcommands, so it takes a bare `OutputFormat` rather than either subcommand's full
+/// argument struct.
+pub fn status(output: OutputFormat, message: &str) {
+    if output == OutputFormat::Text {
+        println!("{message}");
+    }
+}
+
+fn print_record<T: Serialize>(record: &T, output: OutputFormat) {
+    let line = if output == OutputFormat::Json {
+        serde_json::to_string_pretty(record)
+    } else {
+        serde_json::to_string(record)
+    };
+
+    match line {
+        Ok(line) => println!("{line}"),
+        Err(e) => eprintln!("Unable to serialize report record: {e}"),
+    }
+}
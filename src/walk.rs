@@ -1,21 +1,23 @@
 use std::fs::Metadata;
-use std::fs::read_dir;
-use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::args::Args;
+use crate::args::ScanArgs;
+use crate::count;
+use crate::os;
+use crate::report;
 use ahash::AHashSet;
-use ansi_term::Colour::{Green, Red, Yellow};
+use ansi_term::Colour::Green;
+use crossbeam::queue::ArrayQueue;
 use fs_err as fs;
-use human_format::Formatter;
+use ignore::overrides::{Override, OverrideBuilder};
 use ignore::{DirEntry, Error, WalkBuilder, WalkState};
-use indicatif::HumanBytes;
 
 /// Default number of files in a folder to cause alert
 pub const ALERT_COUNT: u64 = 10_000;
@@ -30,6 +32,222 @@ const ERROR_EXIT: i32 = 1;
 /// Default status update period in seconds
 pub const STATUS_SECONDS: u64 = 20;
 
+/// Upper bound on how many offenders the `--top` buffer retains, so memory stays predictable
+/// even on filesystems with millions of flagged directories and a very large `--top` value
+const MAX_BUFFERED_OFFENDERS: usize = 1_000_000;
+
+/// Lower bound on the `--top` buffer's capacity, giving small `--top` values (e.g. `--top 1`)
+/// enough slack that a burst of offenders doesn't immediately start dropping candidates.
+const MIN_BUFFERED_OFFENDERS: usize = 1_024;
+
+/// How much larger than the requested `--top` N the buffer is sized, so the pool of candidates
+/// it sorts from is bigger than just the N ultimately printed.
+const BUFFERED_OFFENDERS_HEADROOM: usize = 16;
+
+/// A single flagged directory collected for the buffered `--top` report instead of being
+/// printed immediately.
+struct BufferedOffender {
+    path: PathBuf,
+    size: u64,
+    approx_files: u64,
+    /// The exact entry count, when offender detection itself required one (see
+    /// [`exact_file_count`]); carried through so the final report doesn't need to re-scan the
+    /// directory.
+    exact_files: Option<u64>,
+    blacklisted: bool,
+    device_id: Option<u64>,
+}
+
+/// Lock-free, bounded collection of offenders gathered while `--top` is active. Once the
+/// queue is full, further offenders are counted as dropped rather than blocking the walk.
+struct OffenderBuffer {
+    queue: ArrayQueue<BufferedOffender>,
+    dropped: AtomicU64,
+}
+
+impl OffenderBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            queue: ArrayQueue::new(capacity),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn push(&self, offender: BufferedOffender) {
+        if self.queue.push(offender).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Collects offenders for `--sort-by-size` so they can be printed sorted by estimated file
+/// count (largest first) once the scan completes, modeled on `fd`'s buffering-vs-streaming
+/// receiver: if the scan is still running once `max_buffer_time` elapses, the buffer flushes
+/// what it has so far and flips to streaming the rest, keeping memory bounded on very large
+/// trees.
+struct SizeSortBuffer {
+    entries: Mutex<Vec<BufferedOffender>>,
+    streaming: AtomicBool,
+    started: Instant,
+    max_buffer_time: Option<Duration>,
+}
+
+impl SizeSortBuffer {
+    fn new(max_buffer_time: Option<u64>) -> Self {
+        Self {
+            entries: Mutex::new(Vec::new()),
+            streaming: AtomicBool::new(false),
+            started: Instant::now(),
+            max_buffer_time: max_buffer_time.map(Duration::from_millis),
+        }
+    }
+
+    /// Records an offender, printing it immediately once streaming has kicked in, otherwise
+    /// buffering it and flipping to streaming (flushing everything buffered so far, sorted by
+    /// estimated file count) once `max_buffer_time` elapses.
+    fn record(&self, args: &ScanArgs, offender: BufferedOffender) {
+        if self.streaming.load(Ordering::Acquire) {
+            print_offender(args, &offender);
+            return;
+        }
+
+        let mut entries = self.entries.lock().expect("size-sort buffer lock poisoned");
+
+        // Another thread may have flipped to streaming while we were waiting on the lock.
+        if self.streaming.load(Ordering::Acquire) {
+            drop(entries);
+            print_offender(args, &offender);
+            return;
+        }
+
+        entries.push(offender);
+
+        let past_deadline = self
+            .max_buffer_time
+            .is_some_and(|limit| self.started.elapsed() >= limit);
+
+        if past_deadline {
+            self.streaming.store(true, Ordering::Release);
+
+            report::status(
+                args.output,
+                "--max-buffer-time elapsed, switching to streaming output for remaining offenders...",
+            );
+
+            drain_sorted(&mut entries, args);
+        }
+    }
+
+    /// Drains and prints any offenders still buffered, sorted by estimated file count. A no-op
+    /// if the buffer already flipped to streaming part way through the scan.
+    fn finish(&self, args: &ScanArgs) {
+        if self.streaming.load(Ordering::Acquire) {
+            return;
+        }
+
+        let mut entries = self.entries.lock().expect("size-sort buffer lock poisoned");
+        drain_sorted(&mut entries, args);
+    }
+}
+
+/// Sorts buffered offenders descending by estimated file count and prints each one, draining
+/// the buffer. Shared by [`SizeSortBuffer::record`]'s deadline flip and
+/// [`SizeSortBuffer::finish`].
+fn drain_sorted(entries: &mut Vec<BufferedOffender>, args: &ScanArgs) {
+    entries.sort_unstable_by(|a, b| b.approx_files.cmp(&a.approx_files));
+
+    for offender in entries.drain(..) {
+        print_offender(args, &offender);
+    }
+}
+
+/// Reports a single buffered offender through [`report::offender`].
+fn print_offender(args: &ScanArgs, offender: &BufferedOffender) {
+    report::offender(
+        args,
+        &offender.path,
+        offender.size,
+        offender.approx_files,
+        offender.exact_files,
+        offender.blacklisted,
+        offender.device_id,
+    );
+}
+
+/// Records a flagged directory through whichever reporting mode is active: the bounded
+/// `--top` buffer takes priority if set, then the unbounded `--sort-by-size` buffer, falling
+/// back to immediate streaming.
+#[allow(clippy::too_many_arguments)]
+fn report_offender(
+    args: &ScanArgs,
+    offender_buffer: Option<&OffenderBuffer>,
+    size_sort_buffer: Option<&SizeSortBuffer>,
+    full_path: &Path,
+    size: u64,
+    approx_files: u64,
+    exact_files: Option<u64>,
+    blacklisted: bool,
+    device_id: Option<u64>,
+) {
+    let offender = BufferedOffender {
+        path: full_path.to_path_buf(),
+        size,
+        approx_files,
+        exact_files,
+        blacklisted,
+        device_id,
+    };
+
+    if let Some(buffer) = offender_buffer {
+        buffer.push(offender);
+    } else if let Some(buffer) = size_sort_buffer {
+        buffer.record(args, offender);
+    } else {
+        print_offender(args, &offender);
+    }
+}
+
+/// Determines the exact entry count for `full_path` when offender detection itself needs one
+/// rather than the `size / size_inode_ratio` heuristic: either `--accurate` was requested
+/// directly, or `run_scan` forced it because the heuristic doesn't hold on this filesystem.
+/// Detection falls back to the heuristic estimate if the exact count can't be retrieved (e.g.
+/// the directory vanished mid-walk).
+fn exact_file_count(args: &ScanArgs, full_path: &Path) -> Option<u64> {
+    args.accurate.then(|| count::count_entries(full_path).ok()).flatten()
+}
+
+/// Builds the glob-based exclusion overrides for `--skip-glob`, following the same
+/// `OverrideBuilder` pattern `fd` uses: each pattern is added negated (`!pattern`) so a match
+/// excludes the entry instead of whitelisting it.
+///
+/// An invalid pattern is logged and skipped while the remaining patterns still apply; if the
+/// override set as a whole cannot be built, that is logged too and an empty override set
+/// (matching nothing) is used, so neither case aborts the scan.
+fn build_skip_glob_overrides(root: &Path, args: &ScanArgs) -> Override {
+    if args.skip_glob.is_empty() {
+        return Override::empty();
+    }
+
+    let mut builder = OverrideBuilder::new(root);
+
+    for pattern in &args.skip_glob {
+        if let Err(e) = builder.add(&format!("!{pattern}")) {
+            report::status(
+                args.output,
+                &format!("Invalid --skip-glob pattern '{pattern}': {e}"),
+            );
+        }
+    }
+
+    builder.build().unwrap_or_else(|e| {
+        report::status(
+            args.output,
+            &format!("Unable to build --skip-glob overrides: {e}"),
+        );
+        Override::empty()
+    })
+}
+
 /// Perform a parallel filesystem search based on specified criteria and arguments.
 ///
 /// # Arguments
@@ -55,14 +273,14 @@ pub const STATUS_SECONDS: u64 = 20;
 /// * `path_metadata` - `&Metadata`
 /// * `size_inode_ratio` - `u64`
 /// * `shutdown_walk` - `&Arc<AtomicBool>`
-/// * `args` - `&Arc<Args>`
+/// * `args` - `&Arc<ScanArgs>`
 /// * Return Type - `u64`
 pub fn parallel_search(
     path: &PathBuf,
     path_metadata: &Metadata,
     size_inode_ratio: u64,
     shutdown_walk: &Arc<AtomicBool>,
-    args: &Arc<Args>,
+    args: &Arc<ScanArgs>,
 ) -> u64 {
     // Create hash set for path exclusions
     let skip_path = &args.skip_path.iter().cloned().collect::<AHashSet<_>>();
@@ -78,19 +296,41 @@ pub fn parallel_search(
     // Processed directory count
     let dir_count = &Arc::new(AtomicU64::new(0));
 
+    // When `--top` is requested, offenders are buffered instead of printed inline and
+    // only the largest `top` of them are reported once the walk completes. The buffer is
+    // sized proportionally to `top` (with sane bounds) rather than unconditionally reserving
+    // the hard maximum, so `--top 10` doesn't pre-allocate space for a million offenders.
+    let offender_buffer = args.top.map(|top| {
+        let capacity = (top as usize)
+            .saturating_mul(BUFFERED_OFFENDERS_HEADROOM)
+            .clamp(MIN_BUFFERED_OFFENDERS, MAX_BUFFERED_OFFENDERS);
+
+        Arc::new(OffenderBuffer::new(capacity))
+    });
+
+    // When `--sort-by-size` is requested, offenders are buffered and printed sorted by
+    // estimated file count once the walk completes, unless `--max-buffer-time` elapses first
+    let size_sort_buffer = args
+        .sort_by_size
+        .then(|| Arc::new(SizeSortBuffer::new(args.max_buffer_time)));
+
     // Status update thread
     if args.updates > 0 {
         let dir_count = dir_count.clone();
         let sleep_delay = args.updates;
+        let args = args.clone();
 
         pool.spawn(move || loop {
             sleep(Duration::from_secs(sleep_delay));
 
             let count = dir_count.load(Ordering::Acquire);
-            println!(
-                "Processed {} directories so far, next update in {} seconds",
-                Green.paint(count.to_string()),
-                sleep_delay
+            report::status(
+                args.output,
+                &format!(
+                    "Processed {} directories so far, next update in {} seconds",
+                    Green.paint(count.to_string()),
+                    sleep_delay
+                ),
             );
         });
     }
@@ -99,15 +339,24 @@ pub fn parallel_search(
     WalkBuilder::new(path)
         .hidden(false)
         .standard_filters(false)
+        .git_ignore(args.respect_ignore)
+        .git_global(args.respect_ignore)
+        .git_exclude(args.respect_ignore)
+        .ignore(args.respect_ignore)
+        .parents(args.respect_ignore)
+        .overrides(build_skip_glob_overrides(path, args))
         .follow_links(args.follow_symlinks)
         .threads(args.threads)
         .build_parallel()
         .run(|| {
+            let offender_buffer = offender_buffer.clone();
+            let size_sort_buffer = size_sort_buffer.clone();
+
             Box::new({
                 move |dir_entry_result| {
                     // Terminate on received interrupt signal
                     if shutdown_walk.load(Ordering::Relaxed) {
-                        println!("Requested program exit, stopping scan...");
+                        report::status(args.output, "Requested program exit, stopping scan...");
 
                         process::exit(ERROR_EXIT);
                     }
@@ -119,11 +368,21 @@ pub fn parallel_search(
                         skip_path,
                         args,
                         dir_count,
+                        offender_buffer.as_deref(),
+                        size_sort_buffer.as_deref(),
                     )
                 }
             })
         });
 
+    if let (Some(buffer), Some(top)) = (&offender_buffer, args.top) {
+        print_top_offenders(buffer, args, top);
+    }
+
+    if let Some(buffer) = &size_sort_buffer {
+        buffer.finish(args);
+    }
+
     dir_count.load(Ordering::Acquire)
 }
 
@@ -136,6 +395,10 @@ pub fn parallel_search(
 /// * `skip_path` - A set of paths to be excluded from scanning.
 /// * `args` - A shared reference to the command-line arguments provided.
 /// * `dir_count` - A shared reference to the atomic counter for visited directories.
+/// * `offender_buffer` - When `--top` is active, the buffer offenders are collected into
+///   instead of being printed immediately.
+/// * `size_sort_buffer` - When `--sort-by-size` is active, the buffer offenders are collected
+///   into instead of being printed immediately.
 ///
 /// # Returns
 /// The state of the directory processing, indicating whether to continue, skip, or stop scanning.
@@ -145,8 +408,10 @@ pub fn parallel_search(
 /// - Increments the visited directory count.
 /// - Skips scanning if the directory is in the skip path list.
 /// - Skips scanning if the directory is on a different filesystem and the `one_filesystem` flag is set.
-/// - Calculates the size and approximate file count of the directory entry.
-/// - Prints warnings and potentially marks the directory as an offender based on file count thresholds.
+/// - Calculates the directory entry's size and approximate file count, falling back to an
+///   exact count for the threshold comparison itself when `--accurate` is set.
+/// - Prints warnings and potentially marks the directory as an offender based on file count thresholds,
+///   or buffers the offender for the final `--top` report when buffering is active.
 /// - Returns the appropriate state for further scanning based on the calculated conditions.
 ///
 /// # Types
@@ -154,16 +419,39 @@ pub fn parallel_search(
 /// * `size_inode_ratio` - `u64`
 /// * `dir_entry_result` - `&Result<DirEntry, ignore::Error>`
 /// * `skip_path` - `&AHashSet<PathBuf>`
-/// * `args` - `&Arc<Args>`
+/// * `args` - `&Arc<ScanArgs>`
 /// * `dir_count` - `&Arc<AtomicU64>`
+/// * `offender_buffer` - `Option<&OffenderBuffer>`
+/// * `size_sort_buffer` - `Option<&SizeSortBuffer>`
 /// * Return Type - `WalkState`
+/// Retrieves a directory's size and device id, using `--use-openat`'s `fstatat`-relative
+/// lookup when enabled and falling back to an ordinary path-based `stat` otherwise, or if the
+/// `fstatat` lookup itself fails (e.g. an unsupported platform, or the entry having just been
+/// removed).
+fn stat_dir_entry(dir_entry: &DirEntry, args: &ScanArgs) -> Option<(u64, Option<u64>)> {
+    if args.use_openat {
+        if let Some(parent) = dir_entry.path().parent() {
+            if let Ok(stat) = os::openat_dir_stat(parent, dir_entry.file_name()) {
+                return Some(stat);
+            }
+        }
+    }
+
+    fs::metadata(dir_entry.path())
+        .ok()
+        .map(|metadata| (os::dir_entry_size(&metadata), os::device_id(&metadata)))
+}
+
+#[allow(clippy::too_many_arguments)]
 fn process_dir_entry(
     path_metadata: &Metadata,
     size_inode_ratio: u64,
     dir_entry_result: &Result<DirEntry, Error>,
     skip_path: &AHashSet<PathBuf>,
-    args: &Arc<Args>,
+    args: &Arc<ScanArgs>,
     dir_count: &Arc<AtomicU64>,
+    offender_buffer: Option<&OffenderBuffer>,
+    size_sort_buffer: Option<&SizeSortBuffer>,
 ) -> WalkState {
     if let Ok(dir_entry) = dir_entry_result {
         if let Some(dir_entry_type) = dir_entry.file_type() {
@@ -180,52 +468,66 @@ fn process_dir_entry(
             if !skip_path.is_empty()
                 && skip_path.contains(&full_path.to_path_buf())
             {
-                println!(
-                    "Skipping further scan at {} as requested",
-                    full_path.display()
+                report::status(
+                    args.output,
+                    &format!("Skipping further scan at {} as requested", full_path.display()),
                 );
 
                 return WalkState::Skip;
             }
 
-            // Retrieve Unix metadata for a given directory
-            if let Ok(dir_entry_metadata) = fs::metadata(full_path) {
+            // Retrieve directory size and device id, either via `--use-openat`'s
+            // `fstatat`-relative lookup or an ordinary path-based `stat`
+            if let Some((size, device_id)) = stat_dir_entry(dir_entry, args) {
                 // If `one_filesystem` flag has been set and if directory is not residing
                 // on the same device as top search path, print warning and abort deeper
                 // scanning
-                if args.one_filesystem
-                    && (dir_entry_metadata.dev() != path_metadata.dev())
-                {
-                    println!(
-                        "Identified filesystem boundary at {}, skipping...",
-                        full_path.display()
+                if args.one_filesystem && device_id != os::device_id(path_metadata) {
+                    report::status(
+                        args.output,
+                        &format!("Identified filesystem boundary at {}, skipping...", full_path.display()),
                     );
 
                     return WalkState::Skip;
                 }
 
-                // Identify size and calculate approximate directory entry count
-                let size = dir_entry_metadata.size();
+                // Calculate approximate directory entry count
                 let approx_files = size / size_inode_ratio;
 
-                // Print count warnings if necessary
-                if approx_files > args.blacklist_threshold {
-                    print_offender(
+                // When `--accurate` is set, either by the user or because `run_scan` forced it
+                // for a filesystem where directory size doesn't track entry count, detection
+                // itself is driven by an exact count instead of the (potentially meaningless)
+                // heuristic above.
+                let exact_files = exact_file_count(args, full_path);
+                let file_count = exact_files.unwrap_or(approx_files);
+
+                // Print count warnings if necessary, or buffer them for the final `--top`
+                // report if buffering is active
+                if file_count > args.blacklist_threshold {
+                    report_offender(
+                        args,
+                        offender_buffer,
+                        size_sort_buffer,
                         full_path,
                         size,
                         approx_files,
-                        args.accurate,
+                        exact_files,
                         true,
+                        device_id,
                     );
 
                     return WalkState::Skip;
-                } else if approx_files > args.alert_threshold {
-                    print_offender(
+                } else if file_count > args.alert_threshold {
+                    report_offender(
+                        args,
+                        offender_buffer,
+                        size_sort_buffer,
                         full_path,
                         size,
                         approx_files,
-                        args.accurate,
+                        exact_files,
                         false,
+                        device_id,
                     );
 
                     return WalkState::Continue;
@@ -237,45 +539,52 @@ fn process_dir_entry(
     WalkState::Continue
 }
 
-#[allow(clippy::cast_precision_loss)]
-/// Prints information about directories that exceed specified thresholds.
-///
-/// This function is called when the estimated number of files in a directory exceeds either the alert or blacklist thresholds.
-/// It outputs details about the directory and its file count, and can optionally mark the directory as an offender based on its size.
+/// Retrieves a directory's size and device id, using `--use-openat`'s `fstatat`-relative
+/// lookup when enabled and falling back to an ordinary path-based `stat` otherwise, or if the
+/// `fstatat` lookup itself fails (e.g. an unsupported platform, or the entry having just been
+/// removed).
+fn stat_dir_entry(dir_entry: &DirEntry, args: &ScanArgs) -> Option<(u64, Option<u64>)> {
+    if args.use_openat {
+        if let Some(parent) = dir_entry.path().parent() {
+            if let Ok(stat) = os::openat_dir_stat(parent, dir_entry.file_name()) {
+                return Some(stat);
+            }
+        }
+    }
+
+    fs::metadata(dir_entry.path())
+        .ok()
+        .map(|metadata| (os::dir_entry_size(&metadata), os::device_id(&metadata)))
+}
+
+/// Drains the buffered offenders collected during a `--top` scan, sorts them descending by
+/// file count, and prints only the largest `top_n` through [`report::offender`].
 ///
 /// # Arguments
-/// * `path` - The path of the directory being evaluated.
-/// * `size` - The size of the directory in bytes.
-/// * `file_count` - The estimated number of files in the directory.
-/// * `accurate` - A boolean flag indicating whether the size estimation is considered accurate.
-/// * `is_blacklisted` - A boolean flag indicating whether the directory exceeds the blacklist threshold.
-fn print_offender(
-    full_path: &Path,
-    size: u64,
-    approx_files: u64,
-    accurate: bool,
-    red_alert: bool,
-) {
-    // Pretty print either the accurate directory count or the approximation
-    let human_files = if accurate {
-        let exact_files = match read_dir(full_path) {
-            Ok(r) => r.count() as u64,
-            Err(_) => approx_files,
-        };
-        Formatter::new().format(exact_files as f64)
-    } else {
-        Formatter::new().format(approx_files as f64)
-    };
+/// * `buffer` - The lock-free buffer offenders were collected into during the walk.
+/// * `args` - A reference to the command-line arguments, providing the output settings.
+/// * `top_n` - The maximum number of offenders to print.
+fn print_top_offenders(buffer: &OffenderBuffer, args: &ScanArgs, top_n: u64) {
+    let mut offenders = Vec::with_capacity(buffer.queue.len());
+    while let Some(offender) = buffer.queue.pop() {
+        offenders.push(offender);
+    }
 
-    println!(
-        "Found directory {} with inode size {} and {}{} files",
-        full_path.display(),
-        HumanBytes(size),
-        if accurate { "" } else { "approx " },
-        if red_alert {
-            Red.paint(human_files)
-        } else {
-            Yellow.paint(human_files)
-        }
+    // Rank by the same count that drove detection: exact when one was taken, else estimated
+    offenders.sort_unstable_by_key(|o| std::cmp::Reverse(o.exact_files.unwrap_or(o.approx_files)));
+    offenders.truncate(top_n as usize);
+
+    report::status(
+        args.output,
+        &format!("Top {} offender(s) by file count:", offenders.len()),
     );
+
+    for offender in &offenders {
+        print_offender(args, offender);
+    }
+
+    let dropped = buffer.dropped.load(Ordering::Relaxed);
+    if dropped > 0 {
+        report::dropped_offenders(args.output, dropped);
+    }
 }